@@ -69,11 +69,27 @@
 )]
 
 use std::{
+    mem,
     num::NonZeroU64,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
-use dashmap::DashMap;
+use dashmap::{mapref::entry::Entry, DashMap};
+use rand::seq::index;
+
+/// How a [`Limit`] paces usages
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Kind {
+    /// Reset-on-expiry fixed window, see [`Limit::new`]
+    Window,
+    /// Leaky/token bucket, see [`Limit::token_bucket`]
+    Bucket {
+        /// How far above [`Limit::count`] the initial allowance is seeded,
+        /// never refilled to again once spent
+        one_time_burst: Option<f32>,
+    },
+}
 
 /// Information about how often something is able to be used
 ///
@@ -86,38 +102,88 @@ use dashmap::DashMap;
 /// ```
 /// twilight_bucket::Limit::new(std::time::Duration::from_secs(60), 10);
 /// ```
+/// Something can be used 10 times in 1 minute, smoothed out instead of
+/// resetting all at once, with an initial burst of 5 extra uses
+/// ```
+/// twilight_bucket::Limit::token_bucket(std::time::Duration::from_secs(60), 10, Some(5.0));
+/// ```
 #[must_use]
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Limit {
     /// How often something can be done [`Limit::count`] times
     duration: Duration,
     /// How many times something can be done in the [`Limit::duration`] period
     count: u16,
+    /// Which pacing strategy this limit uses
+    kind: Kind,
 }
 
 impl Limit {
-    /// Create a new [`Limit`]
+    /// Create a new fixed-window [`Limit`]
+    ///
+    /// Once [`Limit::count`] usages happen inside [`Limit::duration`],
+    /// callers are blocked until the whole window elapses
     pub const fn new(duration: Duration, count: u16) -> Self {
-        Self { duration, count }
+        Self {
+            duration,
+            count,
+            kind: Kind::Window,
+        }
+    }
+
+    /// Create a new token-bucket [`Limit`]
+    ///
+    /// Usage is smoothed instead of arriving in bursts at window boundaries:
+    /// an allowance refills continuously up to [`Limit::count`] at a rate of
+    /// `count / duration`, and each usage spends one token from it
+    ///
+    /// `one_time_burst` seeds the initial allowance above `count`; once it's
+    /// spent, the allowance never refills past `count` again, unless the
+    /// `ID`'s usage is removed by [`Bucket::clean`], after which its next
+    /// usage starts over with the burst
+    pub const fn token_bucket(duration: Duration, count: u16, one_time_burst: Option<f32>) -> Self {
+        Self {
+            duration,
+            count,
+            kind: Kind::Bucket { one_time_burst },
+        }
     }
 }
 
 /// Usage information about an ID
-#[must_use]
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
-struct Usage {
-    /// The last time it was used
-    time: Instant,
-    /// How many times it was used
-    count: u16,
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Usage {
+    /// Usage under a [`Kind::Window`] limit
+    Window {
+        /// The last time it was used
+        time: Instant,
+        /// How many times it was used
+        count: u16,
+    },
+    /// Usage under a [`Kind::Bucket`] limit
+    Bucket {
+        /// The last time the allowance was refilled
+        last_checked: Instant,
+        /// The number of tokens currently available
+        allowance: f32,
+    },
 }
 
 impl Usage {
-    /// Make a `Usage` with now as `time` and 1 as `count`
-    fn new() -> Self {
-        Self {
-            time: Instant::now(),
-            count: 1,
+    /// Make a `Usage` for the given `limit`'s kind, having already spent
+    /// `cost` units
+    #[allow(clippy::float_arithmetic)]
+    fn new(limit: &Limit, cost: u16) -> Self {
+        match limit.kind {
+            Kind::Window => Self::Window {
+                time: Instant::now(),
+                count: cost,
+            },
+            Kind::Bucket { one_time_burst } => Self::Bucket {
+                last_checked: Instant::now(),
+                allowance: f32::from(limit.count) + one_time_burst.unwrap_or(0.0)
+                    - f32::from(cost),
+            },
         }
     }
 }
@@ -136,24 +202,74 @@ impl Usage {
 ///
 /// `ID`s use [`NonZeroU64`](std::num::NonZeroU64) to be compatible with any
 /// kind of ID: users, guilds or even your custom IDs
+///
+/// # Memory
+/// [`Bucket::usages`](Bucket) only grows as new `ID`s are seen, so a bot
+/// seeing many unique users or guilds leaks memory indefinitely unless stale
+/// entries are reclaimed; call [`Bucket::clean`] periodically, or use
+/// [`Bucket::spawn_cleaner`] to do that for you
+///
+/// # Per-`ID` limits
+/// The limit passed to [`Bucket::new`] is just the default: call
+/// [`Bucket::set_limit`] to give specific `ID`s their own limit, for example
+/// to grant premium users a shorter cooldown, without needing a whole
+/// separate [`Bucket`] for them
 #[must_use]
 #[derive(Debug)]
 pub struct Bucket {
-    /// The limit for this bucket
+    /// The default limit for `ID`s without an override
     limit: Limit,
     /// Usage information for IDs
     usages: DashMap<NonZeroU64, Usage>,
+    /// Per-`ID` limits overriding [`Bucket::limit`]
+    overrides: DashMap<NonZeroU64, Limit>,
 }
 
 impl Bucket {
-    /// Create a new [`Bucket`] with the given limit
+    /// Create a new [`Bucket`] with the given default limit
     pub fn new(limit: Limit) -> Self {
         Self {
             limit,
             usages: DashMap::new(),
+            overrides: DashMap::new(),
         }
     }
 
+    /// Give `id` its own limit, overriding the bucket's default
+    ///
+    /// `limit` must use the same pacing mode (fixed window or token bucket)
+    /// as the bucket's default: an id's usage is tracked under whichever
+    /// mode it first registered with, so an override may only change
+    /// [`Limit::duration`]/[`Limit::count`], not switch `id` between modes
+    ///
+    /// # Panics
+    /// If the `id` is 0, or if `limit`'s pacing mode doesn't match the
+    /// bucket's default
+    #[allow(clippy::unwrap_used)]
+    pub fn set_limit(&self, id: u64, limit: Limit) {
+        assert_eq!(
+            mem::discriminant(&limit.kind),
+            mem::discriminant(&self.limit.kind),
+            "override limit must use the same pacing mode as the bucket's default"
+        );
+        self.overrides.insert(id.try_into().unwrap(), limit);
+    }
+
+    /// Remove `id`'s overriding limit, falling back to the bucket's default
+    ///
+    /// # Panics
+    /// If the `id` is 0
+    #[allow(clippy::unwrap_used)]
+    pub fn clear_limit(&self, id: u64) {
+        self.overrides.remove(&id.try_into().unwrap());
+    }
+
+    /// Get the limit that applies to `id`: its override if it has one,
+    /// otherwise the bucket's default
+    fn effective_limit(&self, id: NonZeroU64) -> Limit {
+        self.overrides.get(&id).map_or(self.limit, |limit| *limit)
+    }
+
     /// Register a usage, you should call this every time something you want to
     /// limit is done **after** waiting for the limit
     ///
@@ -173,25 +289,71 @@ impl Bucket {
     ///
     /// # Panics
     /// If the `id` is 0 or when the usage count is over [`u16::MAX`]
-    #[allow(clippy::unwrap_used, clippy::integer_arithmetic)]
     pub fn register(&self, id: u64) {
+        self.register_n(id, 1);
+    }
+
+    /// Register a usage costing `cost` units, you should call this every
+    /// time something you want to limit is done **after** waiting for the
+    /// limit with [`Bucket::limit_duration_n`]
+    ///
+    /// This lets commands of different weight share one [`Bucket`]: a bulk
+    /// operation can cost more units than a trivial one instead of always
+    /// counting as a single usage
+    ///
+    /// A fixed-window usage count saturates at [`u16::MAX`] instead of
+    /// overflowing
+    ///
+    /// # Panics
+    /// If the `id` is 0
+    #[allow(
+        clippy::unwrap_used,
+        clippy::integer_arithmetic,
+        clippy::float_arithmetic
+    )]
+    pub fn register_n(&self, id: u64, cost: u16) {
         let id_non_zero = id.try_into().unwrap();
+        let limit = self.effective_limit(id_non_zero);
         match self.usages.get_mut(&id_non_zero) {
             Some(mut usage) => {
                 let now = Instant::now();
-                usage.count = if now - usage.time > self.limit.duration {
-                    1
-                } else {
-                    usage.count + 1
+                *usage = match *usage {
+                    Usage::Window { time, count } => Usage::Window {
+                        time: now,
+                        count: if now - time > limit.duration {
+                            cost
+                        } else {
+                            count.saturating_add(cost)
+                        },
+                    },
+                    Usage::Bucket {
+                        last_checked,
+                        allowance,
+                    } => Usage::Bucket {
+                        last_checked: now,
+                        allowance: Self::refilled_allowance(&limit, allowance, now - last_checked)
+                            - f32::from(cost),
+                    },
                 };
-                usage.time = now;
             }
             None => {
-                self.usages.insert(id_non_zero, Usage::new());
+                self.usages.insert(id_non_zero, Usage::new(&limit, cost));
             }
         }
     }
 
+    /// Add tokens accrued over `elapsed` to `allowance`, clamped to
+    /// `limit`'s count
+    ///
+    /// An unspent `one_time_burst` can leave `allowance` above `limit.count`;
+    /// that's only ever worked off by spending it, never clamped away by a
+    /// refill, so the cap is `allowance.max(limit.count)`, not `limit.count`
+    #[allow(clippy::float_arithmetic)]
+    fn refilled_allowance(limit: &Limit, allowance: f32, elapsed: Duration) -> f32 {
+        let refill_rate = f32::from(limit.count) / limit.duration.as_secs_f32();
+        (allowance + elapsed.as_secs_f32() * refill_rate).min(allowance.max(f32::from(limit.count)))
+    }
+
     /// Get the duration to wait until the next usage by `id`, returns `None`
     /// if the `id` isn't limited, you should call this **before** registering a
     /// usage
@@ -213,12 +375,256 @@ impl Bucket {
     /// # Panics
     /// If the `id` is 0
     #[must_use]
-    #[allow(clippy::unwrap_in_result, clippy::unwrap_used)]
     pub fn limit_duration(&self, id: u64) -> Option<Duration> {
-        let usage = self.usages.get(&id.try_into().unwrap())?;
-        let elapsed = Instant::now() - usage.time;
-        (usage.count >= self.limit.count && self.limit.duration > elapsed)
-            .then(|| self.limit.duration - elapsed)
+        self.limit_duration_n(id, 1)
+    }
+
+    /// Get the duration to wait until `cost` units are available for `id`,
+    /// returns `None` if that many units are available right now, you should
+    /// call this **before** registering a usage with [`Bucket::register_n`]
+    ///
+    /// Treats the limit as a budget of [`Limit::count`] units per
+    /// [`Limit::duration`], checking whether `cost` units are currently
+    /// available
+    ///
+    /// # Panics
+    /// If the `id` is 0
+    #[must_use]
+    #[allow(
+        clippy::unwrap_in_result,
+        clippy::unwrap_used,
+        clippy::float_arithmetic
+    )]
+    pub fn limit_duration_n(&self, id: u64, cost: u16) -> Option<Duration> {
+        let id_non_zero = id.try_into().unwrap();
+        let limit = self.effective_limit(id_non_zero);
+        let usage = self.usages.get(&id_non_zero)?;
+        match *usage {
+            Usage::Window { time, count } => {
+                let elapsed = Instant::now() - time;
+                (count.saturating_add(cost) > limit.count && limit.duration > elapsed)
+                    .then(|| limit.duration - elapsed)
+            }
+            Usage::Bucket {
+                last_checked,
+                allowance,
+            } => {
+                let elapsed = Instant::now() - last_checked;
+                let allowance = Self::refilled_allowance(&limit, allowance, elapsed);
+                let refill_rate = f32::from(limit.count) / limit.duration.as_secs_f32();
+                (allowance < f32::from(cost))
+                    .then(|| Duration::from_secs_f32((f32::from(cost) - allowance) / refill_rate))
+            }
+        }
+    }
+
+    /// Get how many usages `id` has left before being limited, lets you
+    /// render messages like "3/5 uses left" without duplicating the
+    /// internal window math
+    ///
+    /// # Panics
+    /// If the `id` is 0
+    #[must_use]
+    #[allow(
+        clippy::unwrap_used,
+        clippy::float_arithmetic,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    pub fn available(&self, id: u64) -> u16 {
+        let id_non_zero = id.try_into().unwrap();
+        let limit = self.effective_limit(id_non_zero);
+        let Some(usage) = self.usages.get(&id_non_zero) else {
+            return limit.count;
+        };
+        match *usage {
+            Usage::Window { time, count } => {
+                if Instant::now() - time > limit.duration {
+                    limit.count
+                } else {
+                    limit.count.saturating_sub(count)
+                }
+            }
+            Usage::Bucket {
+                last_checked,
+                allowance,
+            } => Self::refilled_allowance(&limit, allowance, Instant::now() - last_checked) as u16,
+        }
+    }
+
+    /// Get when `id`'s usages will reset back to being fully available,
+    /// returns `None` if it's already fully available
+    ///
+    /// # Panics
+    /// If the `id` is 0
+    #[must_use]
+    #[allow(clippy::unwrap_used, clippy::float_arithmetic)]
+    pub fn reset_at(&self, id: u64) -> Option<Instant> {
+        let id_non_zero = id.try_into().unwrap();
+        let limit = self.effective_limit(id_non_zero);
+        let usage = self.usages.get(&id_non_zero)?;
+        match *usage {
+            Usage::Window { time, count } => {
+                let elapsed = Instant::now() - time;
+                (count > 0 && limit.duration > elapsed).then(|| time + limit.duration)
+            }
+            Usage::Bucket {
+                last_checked,
+                allowance,
+            } => {
+                let elapsed = Instant::now() - last_checked;
+                let allowance = Self::refilled_allowance(&limit, allowance, elapsed);
+                let refill_rate = f32::from(limit.count) / limit.duration.as_secs_f32();
+                (allowance < f32::from(limit.count)).then(|| {
+                    let remaining = (f32::from(limit.count) - allowance) / refill_rate;
+                    Instant::now() + Duration::from_secs_f32(remaining)
+                })
+            }
+        }
+    }
+
+    /// Wait until a usage by `id` is permitted, then register it
+    ///
+    /// This is the common "just wait until I'm allowed" path, encapsulating
+    /// the [`Bucket::limit_duration`], sleep, [`Bucket::register`] dance;
+    /// unlike doing that by hand, it's safe to call from multiple tasks at
+    /// once: each check-then-register runs as one atomic step per `id` (the
+    /// sleep in between checks doesn't hold any lock), so two tasks can't
+    /// both observe a free permit and both proceed
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use twilight_bucket::{Bucket, Limit};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// # let user_id = 123;
+    /// let bucket = Bucket::new(Limit::new(Duration::from_secs(1), 1));
+    /// bucket.acquire(user_id).await;
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    /// If the `id` is 0
+    pub async fn acquire(&self, id: u64) {
+        while let Some(duration) = self.try_reserve(id, 1) {
+            tokio::time::sleep(duration).await;
+        }
+    }
+
+    /// Atomically check whether `cost` units are available for `id` and, if
+    /// so, register them in the same critical section; returns the wait
+    /// [`Duration`] otherwise, mirroring [`Bucket::limit_duration_n`]
+    ///
+    /// This holds the lock on `id`'s shard for the whole check-then-act,
+    /// unlike calling [`Bucket::limit_duration_n`] then
+    /// [`Bucket::register_n`] separately, which leaves a gap for another
+    /// task to also see a free permit in between
+    #[allow(
+        clippy::unwrap_used,
+        clippy::integer_arithmetic,
+        clippy::float_arithmetic
+    )]
+    fn try_reserve(&self, id: u64, cost: u16) -> Option<Duration> {
+        let id_non_zero = id.try_into().unwrap();
+        let limit = self.effective_limit(id_non_zero);
+        let now = Instant::now();
+        match self.usages.entry(id_non_zero) {
+            Entry::Occupied(mut entry) => match *entry.get() {
+                Usage::Window { time, count } => {
+                    let elapsed = now - time;
+                    if elapsed > limit.duration {
+                        entry.insert(Usage::Window {
+                            time: now,
+                            count: cost,
+                        });
+                        None
+                    } else if count.saturating_add(cost) > limit.count {
+                        Some(limit.duration - elapsed)
+                    } else {
+                        entry.insert(Usage::Window {
+                            time: now,
+                            count: count.saturating_add(cost),
+                        });
+                        None
+                    }
+                }
+                Usage::Bucket {
+                    last_checked,
+                    allowance,
+                } => {
+                    let elapsed = now - last_checked;
+                    let allowance = Self::refilled_allowance(&limit, allowance, elapsed);
+                    if allowance < f32::from(cost) {
+                        let refill_rate = f32::from(limit.count) / limit.duration.as_secs_f32();
+                        Some(Duration::from_secs_f32(
+                            (f32::from(cost) - allowance) / refill_rate,
+                        ))
+                    } else {
+                        entry.insert(Usage::Bucket {
+                            last_checked: now,
+                            allowance: allowance - f32::from(cost),
+                        });
+                        None
+                    }
+                }
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(Usage::new(&limit, cost));
+                None
+            }
+        }
+    }
+
+    /// Remove usages whose window has fully elapsed, bounding the memory
+    /// [`Bucket::usages`](Bucket) would otherwise grow to indefinitely
+    ///
+    /// A token-bucket usage counts as elapsed once it's gone unchecked for
+    /// the limit's duration; a removed `ID` gets its `one_time_burst` again on
+    /// its next usage
+    pub fn clean(&self) {
+        let now = Instant::now();
+        self.usages
+            .retain(|id, usage| !self.is_stale(*id, usage, now));
+    }
+
+    /// Like [`Bucket::clean`], but only sweeps `sample_shards` distinct,
+    /// randomly chosen internal shards instead of all of them, keeping the
+    /// cost of a pass bounded regardless of how large
+    /// [`Bucket::usages`](Bucket) has grown
+    ///
+    /// Requires the `dashmap` crate's `raw-api` feature
+    pub fn clean_sampled(&self, sample_shards: usize) {
+        let now = Instant::now();
+        let shards = self.usages.shards();
+        let mut rng = rand::thread_rng();
+        let sampled = index::sample(&mut rng, shards.len(), sample_shards.min(shards.len()));
+        for shard_index in sampled {
+            let mut shard = shards[shard_index].write();
+            shard.retain(|id, usage| !self.is_stale(*id, usage.get(), now));
+        }
+    }
+
+    /// Spawn a task that calls [`Bucket::clean`] every `interval`, until the
+    /// returned handle is aborted; dropping the handle detaches the task
+    /// without stopping it
+    pub fn spawn_cleaner(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let bucket = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                bucket.clean();
+            }
+        })
+    }
+
+    /// Whether `id`'s usage has fully elapsed as of `now`
+    fn is_stale(&self, id: NonZeroU64, usage: &Usage, now: Instant) -> bool {
+        let duration = self.effective_limit(id).duration;
+        match *usage {
+            Usage::Window { time, .. } => now - time > duration,
+            Usage::Bucket { last_checked, .. } => now - last_checked > duration,
+        }
     }
 }
 
@@ -265,4 +671,222 @@ mod tests {
         sleep(bucket.limit.duration).await;
         assert!(bucket.limit_duration(id).is_none());
     }
+
+    #[allow(clippy::unwrap_used)]
+    #[tokio::test]
+    async fn token_bucket() {
+        let bucket = Bucket::new(Limit::token_bucket(Duration::from_secs(5), 5, Some(1.0)));
+        let id = 123;
+
+        for _ in 0_u8..6 {
+            assert!(bucket.limit_duration(id).is_none());
+            bucket.register(id);
+        }
+
+        assert!(bucket.limit_duration(id).unwrap() < bucket.limit.duration);
+        sleep(bucket.limit.duration).await;
+        assert!(bucket.limit_duration(id).is_none());
+    }
+
+    #[tokio::test]
+    async fn token_bucket_one_time_burst_survives_until_spent() {
+        let bucket = Bucket::new(Limit::token_bucket(Duration::from_secs(5), 5, Some(3.0)));
+        let id = 123;
+
+        // count (5) + burst (3): 8 immediate uses permitted before blocking
+        for _ in 0_u8..8 {
+            assert!(bucket.limit_duration(id).is_none());
+            bucket.register(id);
+        }
+        assert!(bucket.limit_duration(id).is_some());
+    }
+
+    #[tokio::test]
+    async fn acquire_waits_for_a_free_permit() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(2), 1));
+        let id = 123;
+
+        bucket.acquire(id).await;
+        assert!(bucket.limit_duration(id).is_some());
+
+        let start = std::time::Instant::now();
+        bucket.acquire(id).await;
+        assert!(start.elapsed() > Duration::from_secs(1));
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[tokio::test]
+    async fn acquire_admits_only_one_concurrent_caller_per_permit() {
+        use std::sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        };
+
+        let bucket = Arc::new(Bucket::new(Limit::new(Duration::from_secs(10), 1)));
+        let id = 123;
+        let admitted = Arc::new(AtomicU32::new(0));
+
+        let handles: Vec<_> = (0..20)
+            .map(|_| {
+                let bucket = Arc::clone(&bucket);
+                let admitted = Arc::clone(&admitted);
+                tokio::spawn(async move {
+                    if tokio::time::timeout(Duration::from_millis(50), bucket.acquire(id))
+                        .await
+                        .is_ok()
+                    {
+                        admitted.fetch_add(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(admitted.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn clean_removes_stale_usages() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(1), 1));
+        let id = 123;
+
+        bucket.register(id);
+        assert_eq!(bucket.usages.len(), 1);
+
+        sleep(bucket.limit.duration * 2).await;
+        bucket.clean();
+        assert_eq!(bucket.usages.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn clean_sampled_sweeps_every_requested_shard_once() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(1), 1));
+
+        for id in 1_u64..=50 {
+            bucket.register(id);
+        }
+        assert_eq!(bucket.usages.len(), 50);
+
+        sleep(bucket.limit.duration * 2).await;
+        // more than any real shard count, so every shard gets swept
+        bucket.clean_sampled(usize::from(u16::MAX));
+        assert_eq!(bucket.usages.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn spawn_cleaner_cleans_on_an_interval() {
+        let bucket = std::sync::Arc::new(Bucket::new(Limit::new(Duration::from_millis(100), 1)));
+        let id = 123;
+
+        bucket.register(id);
+        assert_eq!(bucket.usages.len(), 1);
+
+        let cleaner = bucket.spawn_cleaner(Duration::from_millis(50));
+        sleep(Duration::from_millis(300)).await;
+        cleaner.abort();
+
+        assert_eq!(bucket.usages.len(), 0);
+    }
+
+    #[allow(clippy::unwrap_used)]
+    #[tokio::test]
+    async fn weighted_usage() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(2), 5));
+        let id = 123;
+
+        assert!(bucket.limit_duration_n(id, 3).is_none());
+        bucket.register_n(id, 3);
+        assert!(bucket.limit_duration_n(id, 3).is_some());
+        assert!(bucket.limit_duration_n(id, 2).is_none());
+
+        bucket.register_n(id, 2);
+        assert!(
+            bucket.limit_duration_n(id, 1).unwrap()
+                > bucket.limit.duration - Duration::from_secs_f32(0.1)
+        );
+        sleep(bucket.limit.duration).await;
+        assert!(bucket.limit_duration_n(id, 5).is_none());
+    }
+
+    #[tokio::test]
+    async fn limit_duration_n_does_not_overflow_near_u16_max() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(2), 5));
+        let id = 123;
+
+        bucket.register_n(id, u16::MAX - 2);
+        assert!(bucket.limit_duration_n(id, 10).is_some());
+    }
+
+    #[tokio::test]
+    async fn register_n_does_not_overflow_near_u16_max() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(2), 5));
+        let id = 123;
+
+        bucket.register_n(id, u16::MAX - 2);
+        // used to panic with "attempt to add with overflow"
+        bucket.register_n(id, 10);
+    }
+
+    #[tokio::test]
+    async fn acquire_does_not_overflow_near_u16_max() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(2), 5));
+        let id = 123;
+
+        bucket.register_n(id, u16::MAX - 2);
+        // used to panic with "attempt to add with overflow" instead of waiting
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), bucket.acquire(id))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn available_and_reset_at() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(2), 5));
+        let id = 123;
+
+        assert_eq!(bucket.available(id), 5);
+        assert!(bucket.reset_at(id).is_none());
+
+        for left in (0_u16..5).rev() {
+            bucket.register(id);
+            assert_eq!(bucket.available(id), left);
+            // even partially used, the window isn't fully available again
+            assert!(bucket.reset_at(id).is_some());
+        }
+        assert!(bucket.reset_at(id).is_some());
+
+        sleep(bucket.limit.duration).await;
+        assert_eq!(bucket.available(id), 5);
+        assert!(bucket.reset_at(id).is_none());
+    }
+
+    #[tokio::test]
+    async fn per_id_limit_override() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(5), 1));
+        let default_id = 123;
+        let premium_id = 456;
+
+        bucket.set_limit(premium_id, Limit::new(Duration::from_secs(5), 3));
+
+        bucket.register(default_id);
+        assert!(bucket.limit_duration(default_id).is_some());
+
+        bucket.register(premium_id);
+        assert!(bucket.limit_duration(premium_id).is_none());
+
+        bucket.clear_limit(premium_id);
+        bucket.register(premium_id);
+        assert!(bucket.limit_duration(premium_id).is_some());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "same pacing mode")]
+    async fn set_limit_rejects_mismatched_pacing_mode() {
+        let bucket = Bucket::new(Limit::new(Duration::from_secs(5), 1));
+        bucket.set_limit(123, Limit::token_bucket(Duration::from_secs(5), 1, None));
+    }
 }